@@ -1,9 +1,14 @@
 use log::*;
-use serde::{Deserialize, Serialize};
+use serde::{de::Deserializer, Deserialize, Serialize};
 #[allow(deprecated)]
 use solana_sdk::sysvar::recent_blockhashes;
+#[allow(deprecated)]
+use solana_sdk::sysvar::recent_blockhashes::Entry;
 use solana_sdk::{fee_calculator::FeeCalculator, hash::Hash, timing::timestamp};
-use std::collections::HashMap;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BinaryHeap, HashMap},
+};
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, AbiExample)]
 struct HashAge {
@@ -12,9 +17,32 @@ struct HashAge {
     timestamp: u64,
 }
 
+/// A single entry in the heap used by `recent_blockhashes_sorted`, ordered newest-first
+/// by `hash_height` with ties broken deterministically by the hash bytes.
+#[derive(Eq, PartialEq)]
+struct RecentBlockhash<'a> {
+    hash_height: u64,
+    hash: &'a Hash,
+    lamports_per_signature: u64,
+}
+
+impl<'a> Ord for RecentBlockhash<'a> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.hash_height
+            .cmp(&other.hash_height)
+            .then_with(|| self.hash.cmp(other.hash))
+    }
+}
+
+impl<'a> PartialOrd for RecentBlockhash<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 /// Low memory overhead, so can be cloned for every checkpoint
 #[frozen_abi(digest = "J1fGiMHyiKEBcWE6mfm7grAEGJgYEaVLzcrNZvd37iA2")]
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, AbiExample)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, AbiExample)]
 pub struct BlockhashQueue {
     /// updated whenever an hash is registered
     hash_height: u64,
@@ -29,6 +57,48 @@ pub struct BlockhashQueue {
 
     #[serde(skip)]
     force_calculator: Option<FeeCalculator>,
+
+    /// secondary index from `hash_height` to `(hash, timestamp)`, kept in sync with `ages` so
+    /// `hash_height_to_timestamp` and `hash_for_height` are O(log n) instead of scanning `ages`.
+    /// Not part of the serialized ABI; rebuilt from `ages` on deserialize.
+    #[serde(skip)]
+    hash_height_index: BTreeMap<u64, (Hash, u64)>,
+}
+
+impl<'de> Deserialize<'de> for BlockhashQueue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct BlockhashQueueData {
+            hash_height: u64,
+            last_hash: Option<Hash>,
+            ages: HashMap<Hash, HashAge>,
+            max_age: usize,
+        }
+
+        let BlockhashQueueData {
+            hash_height,
+            last_hash,
+            ages,
+            max_age,
+        } = BlockhashQueueData::deserialize(deserializer)?;
+
+        let hash_height_index = ages
+            .iter()
+            .map(|(hash, age)| (age.hash_height, (*hash, age.timestamp)))
+            .collect();
+
+        Ok(Self {
+            hash_height,
+            last_hash,
+            ages,
+            max_age,
+            force_calculator: None,
+            hash_height_index,
+        })
+    }
 }
 
 impl BlockhashQueue {
@@ -39,6 +109,7 @@ impl BlockhashQueue {
             last_hash: None,
             max_age,
             force_calculator: None,
+            hash_height_index: BTreeMap::new(),
         }
     }
 
@@ -71,6 +142,18 @@ impl BlockhashQueue {
         }
     }
 
+    /// Returns the `lamports_per_signature` fee for `hash` without going through the deprecated
+    /// `FeeCalculator` accessor, honoring the `force_calculator` override.
+    pub fn get_lamports_per_signature(&self, hash: &Hash) -> Option<u64> {
+        if let Some(fee_calculator) = &self.force_calculator {
+            return Some(fee_calculator.lamports_per_signature);
+        }
+
+        self.ages
+            .get(hash)
+            .map(|hash_age| hash_age.fee_calculator.lamports_per_signature)
+    }
+
     /// Check if the age of the hash is within the max_age
     /// return false for any hashes with an age above max_age
     /// return None for any hashes that were not found
@@ -107,6 +190,8 @@ impl BlockhashQueue {
     ) -> bool {
         if !self.ages.contains_key(&hash) {
             let lps = fee_calculator.lamports_per_signature;
+            self.hash_height_index
+                .insert(hash_height, (hash, timestamp));
             self.ages.insert(
                 hash,
                 HashAge {
@@ -134,12 +219,14 @@ impl BlockhashQueue {
     }
 
     pub fn genesis_hash(&mut self, hash: &Hash, fee_calculator: &FeeCalculator) {
+        let timestamp = timestamp();
+        self.hash_height_index.insert(0, (*hash, timestamp));
         self.ages.insert(
             *hash,
             HashAge {
                 fee_calculator: fee_calculator.clone(),
                 hash_height: 0,
-                timestamp: timestamp(),
+                timestamp,
             },
         );
 
@@ -158,34 +245,48 @@ impl BlockhashQueue {
         //  because we verify age.nth every place we check for validity
         let max_age = self.max_age;
         if self.ages.len() >= max_age {
+            let hash_height_index = &mut self.hash_height_index;
             self.ages.retain(|hash, age| {
                 let allow = Self::check_age(hash_height, max_age, age);
                 if !allow {
                     warn!("removing blockhash {}", hash);
+                    hash_height_index.remove(&age.hash_height);
                 }
                 allow
             });
         }
-        self.ages.insert(
+        let timestamp = timestamp();
+        let previous_age = self.ages.insert(
             *hash,
             HashAge {
                 fee_calculator: fee_calculator.clone(),
                 hash_height,
-                timestamp: timestamp(),
+                timestamp,
             },
         );
+        // If `hash` was already registered, its old `hash_height_index` entry is now stale
+        // (it no longer has a matching `ages` entry) and must be dropped before inserting the new one.
+        if let Some(previous_age) = previous_age {
+            self.hash_height_index.remove(&previous_age.hash_height);
+        }
+        self.hash_height_index
+            .insert(hash_height, (*hash, timestamp));
 
         self.last_hash = Some(*hash);
     }
 
     /// Maps a hash height to a timestamp
     pub fn hash_height_to_timestamp(&self, hash_height: u64) -> Option<u64> {
-        for age in self.ages.values() {
-            if age.hash_height == hash_height {
-                return Some(age.timestamp);
-            }
-        }
-        None
+        self.hash_height_index
+            .get(&hash_height)
+            .map(|(_hash, timestamp)| *timestamp)
+    }
+
+    /// Maps a hash height to the hash that was registered at that height
+    pub fn hash_for_height(&self, hash_height: u64) -> Option<Hash> {
+        self.hash_height_index
+            .get(&hash_height)
+            .map(|(hash, _timestamp)| *hash)
     }
 
     #[deprecated(
@@ -199,6 +300,66 @@ impl BlockhashQueue {
             .map(|(k, v)| recent_blockhashes::IterItem(v.hash_height, k, &v.fee_calculator))
     }
 
+    /// Returns up to `limit` most recently registered `(hash_height, hash, lamports_per_signature)`
+    /// triples, ordered newest-first. Building the heap is O(n) in `ages`, but popping the top
+    /// `limit` entries off of it is only O(limit log n), so this is still cheaper than a full
+    /// O(n log n) sort of `ages` on every call. Callers populating the recent-blockhashes sysvar
+    /// should pass `recent_blockhashes::MAX_ENTRIES` (150).
+    pub fn recent_blockhashes_sorted(&self, limit: usize) -> Vec<(u64, &Hash, u64)> {
+        let mut heap: BinaryHeap<RecentBlockhash> = self
+            .ages
+            .iter()
+            .map(|(hash, age)| RecentBlockhash {
+                hash_height: age.hash_height,
+                hash,
+                lamports_per_signature: age.fee_calculator.lamports_per_signature,
+            })
+            .collect();
+
+        let mut sorted = Vec::with_capacity(limit.min(heap.len()));
+        while sorted.len() < limit {
+            match heap.pop() {
+                Some(entry) => {
+                    sorted.push((entry.hash_height, entry.hash, entry.lamports_per_signature))
+                }
+                None => break,
+            }
+        }
+        sorted
+    }
+
+    /// `recent_blockhashes_sorted` with `limit` defaulted to `recent_blockhashes::MAX_ENTRIES`
+    /// (150), matching the recent-blockhashes sysvar's capacity.
+    pub fn recent_blockhashes_sorted_default(&self) -> Vec<(u64, &Hash, u64)> {
+        self.recent_blockhashes_sorted(recent_blockhashes::MAX_ENTRIES)
+    }
+
+    /// Returns up to `limit` recent-blockhashes sysvar `Entry` records, newest-first, reusing the
+    /// same newest-first heap ordering as `recent_blockhashes_sorted`. Lets the runtime refresh the
+    /// sysvar with a single allocation-predictable call instead of iterating `get_recent_blockhashes`
+    /// and re-packing each `IterItem` by hand.
+    #[allow(deprecated)]
+    pub fn sysvar_entries(&self, limit: usize) -> Vec<Entry> {
+        self.recent_blockhashes_sorted(limit)
+            .into_iter()
+            .map(|(_hash_height, hash, lamports_per_signature)| {
+                Entry::new(hash, lamports_per_signature)
+            })
+            .collect()
+    }
+
+    /// Non-deprecated equivalent of `get_recent_blockhashes` for callers that only need the
+    /// `lamports_per_signature` fee rather than a full `FeeCalculator`.
+    pub fn get_lamports_per_signature_entries(&self) -> impl Iterator<Item = (u64, &Hash, u64)> {
+        (&self.ages).iter().map(|(hash, age)| {
+            (
+                age.hash_height,
+                hash,
+                age.fee_calculator.lamports_per_signature,
+            )
+        })
+    }
+
     pub(crate) fn len(&self) -> usize {
         self.max_age
     }
@@ -271,4 +432,137 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_recent_blockhashes_sorted() {
+        let mut blockhash_queue = BlockhashQueue::new(MAX_RECENT_BLOCKHASHES);
+        for i in 0..MAX_RECENT_BLOCKHASHES {
+            let hash = hash(&serialize(&i).unwrap());
+            blockhash_queue.register_hash(&hash, &FeeCalculator::default());
+        }
+
+        // Sorted output is capped at `limit` and ordered newest-first.
+        let limit = 10;
+        let sorted = blockhash_queue.recent_blockhashes_sorted(limit);
+        assert_eq!(sorted.len(), limit);
+        for window in sorted.windows(2) {
+            assert!(window[0].0 > window[1].0);
+        }
+
+        // A limit larger than the queue just returns everything it has.
+        let sorted = blockhash_queue.recent_blockhashes_sorted(MAX_RECENT_BLOCKHASHES * 2);
+        assert_eq!(sorted.len(), MAX_RECENT_BLOCKHASHES);
+    }
+
+    #[test]
+    fn test_recent_blockhashes_sorted_default() {
+        let mut blockhash_queue = BlockhashQueue::new(MAX_RECENT_BLOCKHASHES);
+        for i in 0..MAX_RECENT_BLOCKHASHES {
+            let hash = hash(&serialize(&i).unwrap());
+            blockhash_queue.register_hash(&hash, &FeeCalculator::default());
+        }
+
+        assert_eq!(
+            blockhash_queue.recent_blockhashes_sorted_default(),
+            blockhash_queue.recent_blockhashes_sorted(recent_blockhashes::MAX_ENTRIES)
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_sysvar_entries() {
+        let mut blockhash_queue = BlockhashQueue::new(MAX_RECENT_BLOCKHASHES);
+        for i in 0..MAX_RECENT_BLOCKHASHES {
+            let hash = hash(&serialize(&i).unwrap());
+            let fee_calculator = FeeCalculator {
+                lamports_per_signature: i as u64,
+                ..FeeCalculator::default()
+            };
+            blockhash_queue.register_hash(&hash, &fee_calculator);
+        }
+
+        let limit = 10;
+        let entries = blockhash_queue.sysvar_entries(limit);
+        assert_eq!(entries.len(), limit);
+        // Newest-first: the last hash registered has the highest lamports_per_signature.
+        assert_eq!(
+            entries[0].fee_calculator.lamports_per_signature,
+            (MAX_RECENT_BLOCKHASHES - 1) as u64
+        );
+        for entry in &entries {
+            assert!(blockhash_queue.check_hash(&entry.blockhash));
+        }
+    }
+
+    #[test]
+    fn test_hash_height_index() {
+        let mut hash_queue = BlockhashQueue::new(1);
+        let hash0 = hash(&serialize(&0).unwrap());
+        hash_queue.register_hash(&hash0, &FeeCalculator::default());
+        assert_eq!(hash_queue.hash_for_height(1), Some(hash0));
+        assert!(hash_queue.hash_height_to_timestamp(1).is_some());
+
+        // Registering past max_age should prune the oldest entry from the index too.
+        let hash1 = hash(&serialize(&1).unwrap());
+        hash_queue.register_hash(&hash1, &FeeCalculator::default());
+        let hash2 = hash(&serialize(&2).unwrap());
+        hash_queue.register_hash(&hash2, &FeeCalculator::default());
+        assert_eq!(hash_queue.hash_for_height(1), None);
+        assert_eq!(hash_queue.hash_height_to_timestamp(1), None);
+        assert_eq!(hash_queue.hash_for_height(3), Some(hash2));
+    }
+
+    #[test]
+    fn test_hash_height_index_reregister() {
+        let mut hash_queue = BlockhashQueue::new(100);
+        let hash0 = hash(&serialize(&0).unwrap());
+        hash_queue.register_hash(&hash0, &FeeCalculator::default());
+        assert_eq!(hash_queue.hash_for_height(1), Some(hash0));
+
+        // Re-registering the same hash at a new height must drop its stale index entry,
+        // not just add a new one.
+        hash_queue.register_hash(&hash0, &FeeCalculator::default());
+        assert_eq!(hash_queue.hash_for_height(1), None);
+        assert_eq!(hash_queue.hash_height_to_timestamp(1), None);
+        assert_eq!(hash_queue.hash_for_height(2), Some(hash0));
+    }
+
+    #[test]
+    fn test_get_lamports_per_signature() {
+        let last_hash = Hash::default();
+        let mut hash_queue = BlockhashQueue::new(100);
+        assert_eq!(hash_queue.get_lamports_per_signature(&last_hash), None);
+
+        let fee_calculator = FeeCalculator {
+            lamports_per_signature: 42,
+            ..FeeCalculator::default()
+        };
+        hash_queue.register_hash(&last_hash, &fee_calculator);
+        assert_eq!(hash_queue.get_lamports_per_signature(&last_hash), Some(42));
+
+        hash_queue.force_set_calculator_for_every(FeeCalculator {
+            lamports_per_signature: 7,
+            ..FeeCalculator::default()
+        });
+        assert_eq!(hash_queue.get_lamports_per_signature(&last_hash), Some(7));
+    }
+
+    #[test]
+    fn test_get_lamports_per_signature_entries() {
+        let mut blockhash_queue = BlockhashQueue::new(MAX_RECENT_BLOCKHASHES);
+        assert_eq!(
+            blockhash_queue.get_lamports_per_signature_entries().count(),
+            0
+        );
+
+        let hash = hash(&serialize(&0).unwrap());
+        let fee_calculator = FeeCalculator {
+            lamports_per_signature: 5,
+            ..FeeCalculator::default()
+        };
+        blockhash_queue.register_hash(&hash, &fee_calculator);
+        let mut entries = blockhash_queue.get_lamports_per_signature_entries();
+        assert_eq!(entries.next(), Some((1, &hash, 5)));
+        assert_eq!(entries.next(), None);
+    }
 }